@@ -8,4 +8,25 @@ pub fn lsb(target: u8) -> u8 {
 
 pub fn msb_set(target: u8) -> bool {
     (target >> 7) == 1
+}
+
+// the largest value a 4-byte VLQ can hold (4 groups of 7 bits), matching the 4-byte cap
+// parse::extract_vlq enforces on read.
+pub const MAX_VLQ_VALUE: u32 = 0x0fff_ffff;
+
+// inverse of the VLQ decoding in parse::extract_vlq: split into 7-bit groups, most significant
+// first, with the continuation bit set on every group but the last. callers must ensure
+// `value <= MAX_VLQ_VALUE` themselves - this always produces a valid VLQ but doesn't cap its
+// length, so a larger value silently emits a 5+ byte VLQ that extract_vlq would then reject.
+// see: https://midimusic.github.io/tech/midispec.html#BM1_1
+pub fn write_vlq(value: u32) -> Vec<u8> {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut remaining = value >> 7;
+    while remaining > 0 {
+        groups.push(((remaining & 0x7f) as u8) | 0x80);
+        remaining >>= 7;
+    }
+
+    groups.reverse();
+    groups
 }
\ No newline at end of file
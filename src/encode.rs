@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io::{Write, Error, ErrorKind};
+use std::path::Path;
+use crate::bits;
+use crate::parse::{
+    HeaderData, FileFormat, Event, EventType, MidiEvent, MetaEvent,
+    HEADER_MARKER, TRACK_MARKER, EXPECTED_INFO_SIZE_BYTES,
+    NOTE_OFF_STATUS, NOTE_ON_STATUS,
+};
+
+// writes a HeaderData + per-track events back out as a valid .mid file. `use_running_status`
+// controls whether repeated channel-event statuses are omitted, which is the dominant size win
+// in real files.
+pub fn write(path: &Path, header: &HeaderData, tracks: &[Vec<Event>], use_running_status: bool) -> Result<(), Error> {
+    let mut file = File::create(path)?;
+
+    file.write_all(&HEADER_MARKER.to_be_bytes())?;
+    file.write_all(&(EXPECTED_INFO_SIZE_BYTES as u32).to_be_bytes())?;
+
+    let format: u16 = match header.format {
+        FileFormat::SingleTrack => 0,
+        FileFormat::MultipleTrack => 1,
+        FileFormat::MultipleSong => 2,
+    };
+    file.write_all(&format.to_be_bytes())?;
+    // num_tracks must reflect what we're actually about to write, not header.num_tracks - callers
+    // are free to hand us a tracks slice that's been filtered/merged/split since the header was
+    // read, and a mismatched count would produce a corrupt .mid with no error raised.
+    let num_tracks: u16 = tracks.len().try_into().map_err(|_| {
+        Error::new(ErrorKind::InvalidInput, "too many tracks to encode, num_tracks must fit in u16")
+    })?;
+    file.write_all(&num_tracks.to_be_bytes())?;
+    file.write_all(&header.division.to_be_bytes())?;
+
+    for track in tracks {
+        let body = encode_track(track, use_running_status)?;
+        file.write_all(&TRACK_MARKER.to_be_bytes())?;
+        file.write_all(&(body.len() as u32).to_be_bytes())?;
+        file.write_all(&body)?;
+    }
+
+    Ok(())
+}
+
+fn encode_track(events: &[Event], use_running_status: bool) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    let mut running_status: Option<u8> = None;
+
+    for event in events {
+        if event.delta_time > bits::MAX_VLQ_VALUE {
+            return Err(Error::new(ErrorKind::InvalidInput, "delta_time exceeds the maximum 4-byte VLQ value"));
+        }
+        buf.extend_from_slice(&bits::write_vlq(event.delta_time));
+        match &event.ty {
+            EventType::Midi(midi) => encode_midi(midi, &mut running_status, use_running_status, &mut buf)?,
+            EventType::Meta(meta) => encode_meta(meta, &mut buf)?,
+            EventType::Sysex => return Err(Error::new(ErrorKind::InvalidInput, "writing sysex events is not supported")),
+        }
+    }
+
+    Ok(buf)
+}
+
+fn encode_midi(event: &MidiEvent, running_status: &mut Option<u8>, use_running_status: bool, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let (status, data): (u8, [u8; 2]) = match *event {
+        MidiEvent::NoteOff { note, velocity, channel } => ((NOTE_OFF_STATUS << 4) | channel, [note, velocity]),
+        MidiEvent::NoteOn { note, velocity, channel } => ((NOTE_ON_STATUS << 4) | channel, [note, velocity]),
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "writing this midi event type is not supported yet")),
+    };
+
+    if !(use_running_status && *running_status == Some(status)) {
+        buf.push(status);
+    }
+    buf.extend_from_slice(&data);
+    *running_status = Some(status);
+
+    Ok(())
+}
+
+fn encode_meta(event: &MetaEvent, buf: &mut Vec<u8>) -> Result<(), Error> {
+    buf.push(0xff);
+    match event {
+        MetaEvent::EndOfTrack => {
+            buf.push(0x2f);
+            buf.extend_from_slice(&bits::write_vlq(0));
+        },
+        MetaEvent::SetTempo(tempo) => {
+            buf.push(0x51);
+            buf.extend_from_slice(&bits::write_vlq(3));
+            buf.extend_from_slice(&tempo.to_be_bytes()[1..]);
+        },
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "writing this meta event type is not supported yet")),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+    fn roundtrip(header: &HeaderData, tracks: &[Vec<Event>], use_running_status: bool) -> (HeaderData, Vec<Vec<Event>>) {
+        // tests run concurrently, so each needs its own file rather than one shared per process
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = temp_dir().join(format!("midi_rs_roundtrip_test_{}_{}.mid", std::process::id(), id));
+        write(&path, header, tracks, use_running_status).unwrap();
+        let result = crate::parse::parse(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn num_tracks_is_derived_from_tracks_not_header() {
+        // header claims 5 tracks but only 2 are actually handed to us - the written file must
+        // agree with what was actually written, not the (now stale) header value.
+        let header = HeaderData { format: FileFormat::MultipleTrack, num_tracks: 5, division: 480 };
+        let tracks = vec![
+            vec![Event { ty: EventType::Meta(MetaEvent::EndOfTrack), delta_time: 0 }],
+            vec![Event { ty: EventType::Meta(MetaEvent::EndOfTrack), delta_time: 0 }],
+        ];
+
+        let (parsed_header, parsed_tracks) = roundtrip(&header, &tracks, false);
+
+        assert_eq!(parsed_header.num_tracks, 2);
+        assert_eq!(parsed_tracks.len(), 2);
+    }
+
+    #[test]
+    fn roundtrip_preserves_vlq_boundary_deltas_and_running_status() {
+        let header = HeaderData { format: FileFormat::SingleTrack, num_tracks: 1, division: 480 };
+        // 127/128, 16383/16384, and 2097151/2097152 straddle the 1-, 2-, 3-, and 4-byte VLQ
+        // boundaries (7, 14, 21 bits of continuation data).
+        let track = vec![
+            Event { ty: EventType::Midi(MidiEvent::NoteOn { note: 60, velocity: 100, channel: 0 }), delta_time: 0 },
+            // same status as above - running status should drop the status byte here
+            Event { ty: EventType::Midi(MidiEvent::NoteOn { note: 64, velocity: 90, channel: 0 }), delta_time: 127 },
+            // different status - must re-emit the status byte even with running status enabled
+            Event { ty: EventType::Midi(MidiEvent::NoteOff { note: 60, velocity: 0, channel: 0 }), delta_time: 128 },
+            // different channel - same message type, different status byte
+            Event { ty: EventType::Midi(MidiEvent::NoteOn { note: 67, velocity: 80, channel: 1 }), delta_time: 16383 },
+            Event { ty: EventType::Meta(MetaEvent::SetTempo(500_000)), delta_time: 16384 },
+            Event { ty: EventType::Midi(MidiEvent::NoteOff { note: 67, velocity: 0, channel: 1 }), delta_time: 2_097_151 },
+            Event { ty: EventType::Midi(MidiEvent::NoteOff { note: 64, velocity: 0, channel: 0 }), delta_time: 2_097_152 },
+            Event { ty: EventType::Meta(MetaEvent::EndOfTrack), delta_time: 0 },
+        ];
+
+        let (parsed_header, parsed_tracks) = roundtrip(&header, std::slice::from_ref(&track), true);
+
+        assert_eq!(parsed_header.division, 480);
+        assert_eq!(parsed_tracks, vec![track]);
+    }
+
+    #[test]
+    fn delta_time_beyond_vlq_range_is_rejected_instead_of_writing_an_unreadable_file() {
+        let header = HeaderData { format: FileFormat::SingleTrack, num_tracks: 1, division: 480 };
+        let track = vec![Event { ty: EventType::Meta(MetaEvent::EndOfTrack), delta_time: bits::MAX_VLQ_VALUE + 1 }];
+
+        let path = temp_dir().join(format!("midi_rs_vlq_overflow_test_{}.mid", std::process::id()));
+        let result = write(&path, &header, &[track], false);
+
+        assert!(result.is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::io::{BufReader, Read, Error, ErrorKind};
 use std::path::Path;
 use std::fs::File;
@@ -5,73 +6,174 @@ use crate::bits;
 
 // MIDI SPEC: https://ccrma.stanford.edu/~craig/14q/midifile/MidiFileFormat.html
 // or better: https://midimusic.github.io/tech/midispec.html
-const HEADER_MARKER: u32 = 0x4d546864;
-const TRACK_MARKER: u32 = 0x4d54726b;
-const EXPECTED_INFO_SIZE_BYTES: usize = 6;
+pub(crate) const HEADER_MARKER: u32 = 0x4d546864;
+pub(crate) const TRACK_MARKER: u32 = 0x4d54726b;
+pub(crate) const EXPECTED_INFO_SIZE_BYTES: usize = 6;
 // midi event tags
-const NOTE_OFF_STATUS: u8 = 0b1000;
-const NOTE_ON_STATUS: u8 = 0b1001;
-// unused midi event tags we have for skipping bytes
-// const POLY_KEY_PRESSURE_STATUS: u8 = 0b1010;
-// const CONTROL_CHANGE_STATUS: u8 = 0b1011;
-const PROGRAM_CHANGE_STATUS: u8 = 0b1100;
-const CHANNEL_PRESSURE_STATUS: u8 = 0b1101;
-// const PITCH_WHEEL_CHANGE_STATUS: u8 = 0b1110;
+pub(crate) const NOTE_OFF_STATUS: u8 = 0b1000;
+pub(crate) const NOTE_ON_STATUS: u8 = 0b1001;
+pub(crate) const POLY_KEY_PRESSURE_STATUS: u8 = 0b1010;
+pub(crate) const CONTROL_CHANGE_STATUS: u8 = 0b1011;
+pub(crate) const PROGRAM_CHANGE_STATUS: u8 = 0b1100;
+pub(crate) const CHANNEL_PRESSURE_STATUS: u8 = 0b1101;
+pub(crate) const PITCH_WHEEL_CHANGE_STATUS: u8 = 0b1110;
 const SYSTEM_MESSAGE_STATUS: u8 = 0b1111;
 
-#[derive(PartialEq)]
-enum FileFormat {
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum FileFormat {
     SingleTrack,
     MultipleTrack,
     MultipleSong
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum MetaEvent {
     Unimplemented,
     EndOfTrack,
-    SetTempo(u32)
+    SetTempo(u32),
+    TrackName(String),
+    InstrumentName(String),
+    Lyric(String),
+    Marker(String),
+    TimeSignature { numerator: u8, denominator_pow2: u8, clocks_per_click: u8, notated_32nd_per_quarter: u8 },
+    KeySignature { sharps_flats: i8, minor: bool },
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum MidiEvent {
     Unimplemented,
     NoteOn { note: u8, velocity: u8, channel: u8 } ,
     NoteOff { note: u8, velocity: u8, channel: u8 } ,
-    //TODO: implement these, for now just note on and note off
-    // ProgramChange(u8),
-    // ControlChange(u8, u8),
-    // PitchBend(u16),
+    PolyKeyPressure { channel: u8, note: u8, pressure: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+    ChannelPressure { channel: u8, pressure: u8 },
+    // 14-bit value, combined from the two 7-bit data bytes as lsb | (msb << 7)
+    PitchBend { channel: u8, value: u16 },
 }
 
-#[derive(Debug)]
-enum EventType {
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum EventType {
     //sysex events aren't useful to us for our toy synth so we just skip them, they're basically just noops
     Sysex,
     Meta(MetaEvent),
     Midi(MidiEvent),
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct Event {
-    ty: EventType,
-    delta_time: u32
+    pub(crate) ty: EventType,
+    pub(crate) delta_time: u32
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct HeaderData {
-    format: FileFormat,
-    num_tracks: u16,
+    pub(crate) format: FileFormat,
+    pub(crate) num_tracks: u16,
     // used for timing
-    division: u16
+    pub(crate) division: u16
 }
 
 
-pub fn parse(path: &Path) -> Result<(HeaderData, Vec<Event>), Error>
+pub fn parse(path: &Path) -> Result<(HeaderData, Vec<Vec<Event>>), Error>
 {
     assert!(path.exists());
-    let file = File::open(path)?; 
+    let file = File::open(path)?;
     let mut reader = BufReader::new(file);
 
-    Ok( (parse_header(&mut reader)?, parse_track(&mut reader)?) ) 
+    let header = parse_header(&mut reader)?;
+    let mut tracks = Vec::with_capacity(header.num_tracks as usize);
+    for _ in 0..header.num_tracks {
+        tracks.push(parse_track(&mut reader)?);
+    }
+
+    Ok( (header, tracks) )
+}
+
+// default tempo per the spec: 120 bpm, i.e. 500000 microseconds per quarter note
+const DEFAULT_TEMPO_US_PER_QUARTER: u32 = 500_000;
+
+// walks a track's events, accumulating delta times into absolute microseconds. `division` comes
+// straight from HeaderData: if its high bit is clear, the low 15 bits are ticks-per-quarter-note
+// and we convert ticks using the current tempo (updated whenever a SetTempo meta event is seen,
+// starting at 500000us/quarter); if the high bit is set, the upper byte is a signed SMPTE frame
+// rate and the lower byte is ticks-per-frame, giving a tempo-independent ticks-per-second.
+// see: https://midimusic.github.io/tech/midispec.html#BMA1_2
+pub fn absolute_times_micros(division: u16, track: Vec<Event>) -> Result<Vec<(u64, EventType)>, Error> {
+    let smpte = bits::msb_set((division >> 8) as u8);
+    let mut tempo = DEFAULT_TEMPO_US_PER_QUARTER;
+    let mut abs_time_us: u64 = 0;
+
+    track.into_iter().map(|event| {
+        abs_time_us += ticks_to_micros(event.delta_time, division, smpte, tempo)?;
+
+        if let EventType::Meta(MetaEvent::SetTempo(new_tempo)) = &event.ty {
+            tempo = *new_tempo;
+        }
+
+        Ok((abs_time_us, event.ty))
+    }).collect()
+}
+
+fn ticks_to_micros(ticks: u32, division: u16, smpte: bool, tempo: u32) -> Result<u64, Error> {
+    if smpte {
+        // upper byte is a signed frame rate (-24, -25, -29 for 29.97 drop-frame, or -30),
+        // lower byte is ticks-per-frame; timing here is independent of tempo
+        let fps = ((division >> 8) as u8 as i8).unsigned_abs() as u64;
+        let ticks_per_frame = u64::from(division & 0xff);
+        if fps == 0 || ticks_per_frame == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "trying to read malformed file - SMPTE division must have a non-zero frame rate and ticks-per-frame")
+            );
+        }
+        Ok(u64::from(ticks) * 1_000_000 / (fps * ticks_per_frame))
+    } else {
+        let ticks_per_quarter = u64::from(division & 0x7fff);
+        if ticks_per_quarter == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "trying to read malformed file - ticks-per-quarter-note division must be non-zero")
+            );
+        }
+        Ok(u64::from(ticks) * u64::from(tempo) / ticks_per_quarter)
+    }
+}
+
+// merges the per-track event streams produced by `parse` into a single chronologically sorted
+// stream, the way a sequencer would: each track is reduced to its own cursor of
+// (absolute_tick, EventType), and at every step we emit whichever track's next event is due
+// soonest, breaking ties by track index.
+pub fn merge_tracks(tracks: Vec<Vec<Event>>) -> Vec<(u32, usize, EventType)> {
+    let mut cursors: Vec<VecDeque<(u32, EventType)>> = tracks.into_iter()
+        .map(to_absolute_ticks)
+        .map(VecDeque::from)
+        .collect();
+
+    let mut merged = Vec::new();
+    loop {
+        let next_track = cursors.iter().enumerate()
+            .filter_map(|(track, events)| events.front().map(|&(tick, _)| (tick, track)))
+            .min();
+
+        let (tick, track) = match next_track {
+            Some(t) => t,
+            None => break,
+        };
+
+        let (_, ty) = cursors[track].pop_front().unwrap();
+        merged.push((tick, track, ty));
+    }
+
+    merged
+}
+
+fn to_absolute_ticks(track: Vec<Event>) -> Vec<(u32, EventType)> {
+    let mut abs_tick: u32 = 0;
+    track.into_iter().map(|event| {
+        abs_tick += event.delta_time;
+        (abs_tick, event.ty)
+    }).collect()
 }
 
 fn parse_header(reader: &mut BufReader<File>) -> Result<HeaderData, Error>
@@ -129,15 +231,15 @@ fn parse_track(reader: &mut BufReader<File>) -> Result<Vec<Event>, Error> {
         );
     }
 
-    //TODO: wrap this all in a while loop, for now we just read one track
     let mut length_buf: [u8; 4] = [0u8; 4];
     reader.read_exact(&mut length_buf)?;
-    
+
     // there are only 32 bytes for the size but vec! needs a usize
     let length = u32::from_be_bytes(length_buf) as usize;
     let mut track_buf = vec![0u8; length];
     reader.read_exact(&mut track_buf)?;
     let mut cur: &[u8] = &track_buf;
+    // running status never carries across a track boundary, so this always starts fresh
     let mut running_status: Option<u8> = None;
     let mut events: Vec<Event> = vec![];
     while cur.len() > 0 {
@@ -240,8 +342,39 @@ fn extract_meta(bytes: &mut &[u8]) -> Result<EventType, Error> {
 
             MetaEvent::SetTempo(tempo)
         },
+        0x03 => MetaEvent::TrackName(String::from_utf8_lossy(&bytes[0..len]).into_owned()),
+        0x04 => MetaEvent::InstrumentName(String::from_utf8_lossy(&bytes[0..len]).into_owned()),
+        0x05 => MetaEvent::Lyric(String::from_utf8_lossy(&bytes[0..len]).into_owned()),
+        0x06 => MetaEvent::Marker(String::from_utf8_lossy(&bytes[0..len]).into_owned()),
+        0x58 => {
+            // time signature is 4 bytes long always
+            if len != 4 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "TimeSignature meta event must have length 4",
+                ));
+            }
+
+            MetaEvent::TimeSignature {
+                numerator: bytes[0],
+                denominator_pow2: bytes[1],
+                clocks_per_click: bytes[2],
+                notated_32nd_per_quarter: bytes[3],
+            }
+        },
+        0x59 => {
+            // key signature is 2 bytes long always
+            if len != 2 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "KeySignature meta event must have length 2",
+                ));
+            }
+
+            MetaEvent::KeySignature { sharps_flats: bytes[0] as i8, minor: bytes[1] != 0 }
+        },
         // these are all of the meta events i'm not implementing cause they're not that interesting or super niche
-        0x01..=0x07 | 0x54 | 0x58 | 0x59 | 0x7f => MetaEvent::Unimplemented,
+        0x01 | 0x02 | 0x07 | 0x54 | 0x7f => MetaEvent::Unimplemented,
         _ => {
             return Err(Error::new(
                     ErrorKind::InvalidData,
@@ -274,8 +407,19 @@ fn extract_midi(running_status: &mut Option<u8>, first_byte: u8, bytes: &mut &[u
     })?;
 
     match bits::msb(status) {
-        NOTE_OFF_STATUS => Ok(EventType::Midi(MidiEvent::NoteOff { note: extract_byte(bytes)?, velocity: extract_byte(bytes)?, channel: bits::lsb(first_byte) })),
-        NOTE_ON_STATUS => Ok(EventType::Midi(MidiEvent::NoteOn { note: extract_byte(bytes)?, velocity: extract_byte(bytes)?, channel: bits::lsb(first_byte) })),
+        // same running-status handling as the other channel voice messages below: when running
+        // status applies, first_byte is already the first data byte (there's no status byte to
+        // have consumed), not an extra byte to discard.
+        NOTE_OFF_STATUS => {
+            let note = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            let velocity = extract_byte(bytes)?;
+            Ok(EventType::Midi(MidiEvent::NoteOff { note, velocity, channel: bits::lsb(status) }))
+        },
+        NOTE_ON_STATUS => {
+            let note = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            let velocity = extract_byte(bytes)?;
+            Ok(EventType::Midi(MidiEvent::NoteOn { note, velocity, channel: bits::lsb(status) }))
+        },
         SYSTEM_MESSAGE_STATUS => {
             // skipping bits as appropriate for each system message on the tiny off chance they pop up
             match bits::lsb(status) {
@@ -289,21 +433,267 @@ fn extract_midi(running_status: &mut Option<u8>, first_byte: u8, bytes: &mut &[u
             }
             Ok(EventType::Midi(MidiEvent::Unimplemented))
         },
-        PROGRAM_CHANGE_STATUS | CHANNEL_PRESSURE_STATUS => {
-            // if we're not using running status, that means that the byte that was already extracted by the 
-            // extract_event func is a status byte. if we are using running status, then this data byte
-            // was already extracted by extract_event. same thing follows for the last arm below
-            if !using_running_status { extract_byte(bytes)?; }
-            // we've already consumed 
-            Ok(EventType::Midi(MidiEvent::Unimplemented))
+        POLY_KEY_PRESSURE_STATUS => {
+            // if we're not using running status, the byte already extracted by extract_event is the
+            // status byte and the note is still ahead of us; if we are using running status, that byte
+            // *is* the note, since there's no status byte to consume. same thing follows below.
+            let note = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            let pressure = extract_byte(bytes)?;
+            Ok(EventType::Midi(MidiEvent::PolyKeyPressure { channel: bits::lsb(status), note, pressure }))
+        },
+        CONTROL_CHANGE_STATUS => {
+            let controller = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            let value = extract_byte(bytes)?;
+            Ok(EventType::Midi(MidiEvent::ControlChange { channel: bits::lsb(status), controller, value }))
+        },
+        PROGRAM_CHANGE_STATUS => {
+            let program = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            Ok(EventType::Midi(MidiEvent::ProgramChange { channel: bits::lsb(status), program }))
+        },
+        CHANNEL_PRESSURE_STATUS => {
+            let pressure = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            Ok(EventType::Midi(MidiEvent::ChannelPressure { channel: bits::lsb(status), pressure }))
+        },
+        PITCH_WHEEL_CHANGE_STATUS => {
+            let data_lsb = if using_running_status { first_byte } else { extract_byte(bytes)? };
+            let data_msb = extract_byte(bytes)?;
+            let value = u16::from(data_lsb) | (u16::from(data_msb) << 7);
+            Ok(EventType::Midi(MidiEvent::PitchBend { channel: bits::lsb(status), value }))
+        },
+        _ => unreachable!("status nibble {:#06b} is not a valid channel voice or system message tag", bits::msb(status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn smpte_division(fps: i8, ticks_per_frame: u8) -> u16 {
+        u16::from_be_bytes([fps as u8, ticks_per_frame])
+    }
+
+    static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+    // builds a minimal well-formed .mid file (format 1) out of raw, already-encoded track
+    // bodies, so parse()'s own track-chunk loop can be exercised without going through encode.rs.
+    fn write_midi_file(track_bodies: &[Vec<u8>], division: u16) -> std::path::PathBuf {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("midi_rs_parse_test_{}_{}.mid", std::process::id(), id));
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&HEADER_MARKER.to_be_bytes());
+        bytes.extend_from_slice(&(EXPECTED_INFO_SIZE_BYTES as u32).to_be_bytes());
+        bytes.extend_from_slice(&1u16.to_be_bytes()); // format 1 (multiple tracks)
+        bytes.extend_from_slice(&(track_bodies.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&division.to_be_bytes());
+        for body in track_bodies {
+            bytes.extend_from_slice(&TRACK_MARKER.to_be_bytes());
+            bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(body);
         }
-        _ => {
-            // see comment in above (PROGRAM_CHANGE_STATUS | CHANNEL_PRESSURE_STATUS) arm!
-            if !using_running_status { extract_byte(bytes)?; }
-            extract_byte(bytes)?;
-            Ok(EventType::Midi(MidiEvent::Unimplemented))
+
+        std::fs::write(&path, &bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn parse_reads_every_track_in_order_including_an_empty_one() {
+        // track 0: NoteOn then EndOfTrack
+        let track0 = vec![0x00, 0x90, 60, 100, 0x00, 0xff, 0x2f, 0x00];
+        // track 1: a zero-length chunk, which should decode to no events rather than erroring
+        let track1: Vec<u8> = vec![];
+
+        let path = write_midi_file(&[track0, track1], 480);
+        let result = parse(&path);
+        std::fs::remove_file(&path).unwrap();
+        let (header, tracks) = result.unwrap();
+
+        assert_eq!(header.num_tracks, 2);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].len(), 2);
+        assert!(tracks[1].is_empty());
+    }
+
+    #[test]
+    fn parse_does_not_carry_running_status_across_a_track_boundary() {
+        // track 0 leaves running status set to NoteOn/channel 0 on its last event
+        let track0 = vec![0x00, 0x90, 60, 100, 0x00, 0xff, 0x2f, 0x00];
+        // track 1 opens with a bare data byte, relying on running status from track 0 - this
+        // must fail, since running status never carries across a track boundary
+        let track1 = vec![0x00, 61, 101];
+
+        let path = write_midi_file(&[track0, track1], 480);
+        let result = parse(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn ticks_to_micros_tempo_based() {
+        // 480 ticks/quarter at the default 120bpm tempo (500000us/quarter): half a quarter
+        // note's worth of ticks is half the tempo in microseconds.
+        assert_eq!(ticks_to_micros(240, 480, false, 500_000).unwrap(), 250_000);
+        assert_eq!(ticks_to_micros(480, 480, false, 500_000).unwrap(), 500_000);
+        // tempo change should scale the result directly
+        assert_eq!(ticks_to_micros(480, 480, false, 1_000_000).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn ticks_to_micros_smpte_24_25_30fps() {
+        assert_eq!(ticks_to_micros(24 * 4, smpte_division(-24, 4), true, 500_000).unwrap(), 1_000_000);
+        assert_eq!(ticks_to_micros(25 * 4, smpte_division(-25, 4), true, 500_000).unwrap(), 1_000_000);
+        assert_eq!(ticks_to_micros(30 * 4, smpte_division(-30, 4), true, 500_000).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn ticks_to_micros_smpte_2997_drop_frame() {
+        // -29 in the division's frame-rate byte means 29.97 drop-frame per spec, but the
+        // field only ever encodes the integer 29 - timing math uses that integer rate, it
+        // does not apply the 1000/1001 drop-frame correction.
+        let division = smpte_division(-29, 4);
+        assert_eq!(ticks_to_micros(29 * 4, division, true, 500_000).unwrap(), 1_000_000);
+        assert_eq!(ticks_to_micros(29 * 4 * 2, division, true, 500_000).unwrap(), 2_000_000);
+        // tempo is irrelevant once we're in SMPTE mode
+        assert_eq!(ticks_to_micros(29 * 4, division, true, 120_000).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn ticks_to_micros_rejects_zero_division_instead_of_dividing_by_zero() {
+        // non-SMPTE division of 0 ticks-per-quarter-note would divide by zero
+        assert!(ticks_to_micros(10, 0, false, 500_000).is_err());
+        // SMPTE division with a zero ticks-per-frame byte (e.g. the malformed 0xe200) would
+        // also divide by zero, even though the frame rate itself is non-zero
+        assert!(ticks_to_micros(10, smpte_division(-30, 0), true, 500_000).is_err());
+    }
+
+    #[test]
+    fn absolute_times_micros_updates_tempo_mid_track() {
+        let track = vec![
+            Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 480 },
+            Event { ty: EventType::Meta(MetaEvent::SetTempo(1_000_000)), delta_time: 0 },
+            Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 480 },
+        ];
+
+        let times: Vec<u64> = absolute_times_micros(480, track).unwrap().into_iter().map(|(t, _)| t).collect();
+
+        // first event uses the default 500000us/quarter tempo, the third uses the new tempo
+        // set by the SetTempo event that sits between them
+        assert_eq!(times, vec![500_000, 500_000, 1_500_000]);
+    }
+
+    #[test]
+    fn absolute_times_micros_propagates_the_division_error() {
+        let track = vec![Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 1 }];
+        assert!(absolute_times_micros(0, track).is_err());
+    }
+
+    #[test]
+    fn merge_tracks_orders_by_tick_and_breaks_ties_by_track_index() {
+        let track0 = vec![
+            Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 0 },  // tick 0
+            Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 20 }, // tick 20
+        ];
+        let track1 = vec![
+            Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 0 }, // tick 0, ties with track0
+            Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 5 }, // tick 5
+        ];
+
+        let merged = merge_tracks(vec![track0, track1]);
+        let ticks_and_tracks: Vec<(u32, usize)> = merged.into_iter().map(|(tick, track, _)| (tick, track)).collect();
+
+        // tick 0 is a tie between track 0 and track 1 - the lower track index must come first,
+        // and each track's own cursor must advance independently of the other's
+        assert_eq!(ticks_and_tracks, vec![(0, 0), (0, 1), (5, 1), (20, 0)]);
+    }
+
+    #[test]
+    fn merge_tracks_handles_an_empty_track() {
+        let track0 = vec![Event { ty: EventType::Midi(MidiEvent::Unimplemented), delta_time: 10 }];
+        let track1: Vec<Event> = vec![];
+
+        let merged = merge_tracks(vec![track0, track1]);
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].0, merged[0].1), (10, 0));
+    }
+
+    fn decode_meta(event_type: u8, data: &[u8]) -> MetaEvent {
+        let mut buf = vec![event_type];
+        buf.extend_from_slice(&bits::write_vlq(data.len() as u32));
+        buf.extend_from_slice(data);
+        let mut cur: &[u8] = &buf;
+        match extract_meta(&mut cur).unwrap() {
+            EventType::Meta(meta) => meta,
+            other => panic!("expected a meta event, got {:?}", other),
         }
     }
-}
 
+    #[test]
+    fn extract_meta_text_events() {
+        assert_eq!(decode_meta(0x03, b"Piano"), MetaEvent::TrackName("Piano".to_string()));
+        assert_eq!(decode_meta(0x04, b"Grand Piano"), MetaEvent::InstrumentName("Grand Piano".to_string()));
+        assert_eq!(decode_meta(0x05, b"la la la"), MetaEvent::Lyric("la la la".to_string()));
+        assert_eq!(decode_meta(0x06, b"Verse 1"), MetaEvent::Marker("Verse 1".to_string()));
+    }
+
+    #[test]
+    fn extract_meta_time_signature() {
+        // 3/8 time, 24 MIDI clocks per click, 8 notated 32nds per quarter note
+        let event = decode_meta(0x58, &[3, 3, 24, 8]);
+        assert_eq!(event, MetaEvent::TimeSignature {
+            numerator: 3, denominator_pow2: 3, clocks_per_click: 24, notated_32nd_per_quarter: 8,
+        });
+    }
+
+    #[test]
+    fn extract_meta_key_signature_sharps_and_flats() {
+        // 2 sharps, major
+        assert_eq!(decode_meta(0x59, &[2, 0]), MetaEvent::KeySignature { sharps_flats: 2, minor: false });
+        // 3 flats is encoded as the byte -3 (0xfd), minor
+        assert_eq!(decode_meta(0x59, &[0xfd, 1]), MetaEvent::KeySignature { sharps_flats: -3, minor: true });
+    }
+
+    fn decode_midi(running_status: &mut Option<u8>, first_byte: u8, rest: &[u8]) -> MidiEvent {
+        let mut cur: &[u8] = rest;
+        match extract_midi(running_status, first_byte, &mut cur).unwrap() {
+            EventType::Midi(midi) => midi,
+            other => panic!("expected a midi event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn extract_midi_control_program_and_channel_pressure() {
+        assert_eq!(
+            decode_midi(&mut None, 0xb3, &[7, 100]),
+            MidiEvent::ControlChange { channel: 3, controller: 7, value: 100 }
+        );
+        assert_eq!(decode_midi(&mut None, 0xc2, &[5]), MidiEvent::ProgramChange { channel: 2, program: 5 });
+        assert_eq!(decode_midi(&mut None, 0xd1, &[64]), MidiEvent::ChannelPressure { channel: 1, pressure: 64 });
+        assert_eq!(
+            decode_midi(&mut None, 0xa0, &[60, 127]),
+            MidiEvent::PolyKeyPressure { channel: 0, note: 60, pressure: 127 }
+        );
+    }
+
+    #[test]
+    fn extract_midi_reuses_running_status_for_non_note_events() {
+        // running status is CC/channel 3 - first_byte is itself the controller number, since
+        // there's no status byte ahead of it
+        let mut running_status = Some(0xb3u8);
+        assert_eq!(
+            decode_midi(&mut running_status, 7, &[100]),
+            MidiEvent::ControlChange { channel: 3, controller: 7, value: 100 }
+        );
+    }
+
+    #[test]
+    fn extract_midi_pitch_bend_combines_14_bit_value() {
+        // lsb=0x7f, msb=0x7f -> maximum bend value
+        assert_eq!(decode_midi(&mut None, 0xe0, &[0x7f, 0x7f]), MidiEvent::PitchBend { channel: 0, value: 0x3fff });
+        // lsb=0, msb=0x40 -> centered bend value
+        assert_eq!(decode_midi(&mut None, 0xe0, &[0x00, 0x40]), MidiEvent::PitchBend { channel: 0, value: 0x2000 });
+    }
+}
 
@@ -0,0 +1,143 @@
+use crate::bits;
+use crate::parse::{
+    MidiEvent,
+    NOTE_OFF_STATUS, NOTE_ON_STATUS, POLY_KEY_PRESSURE_STATUS, CONTROL_CHANGE_STATUS,
+    PROGRAM_CHANGE_STATUS, CHANNEL_PRESSURE_STATUS, PITCH_WHEEL_CHANGE_STATUS,
+};
+
+// real-time MIDI input has no delta times or track wrapper, just a raw byte stream, so we can't
+// reuse parse::extract_midi directly - that assumes a full in-memory buffer and errors out on a
+// short read, whereas a live stream just means "wait for more bytes". this holds the same
+// running-status state across calls to `push` so messages split across reads decode correctly.
+pub struct StreamParser {
+    running_status: Option<u8>,
+    pending: Vec<u8>,
+}
+
+impl StreamParser {
+    pub fn new() -> StreamParser {
+        StreamParser { running_status: None, pending: Vec::new() }
+    }
+
+    // decodes as many complete channel-voice messages as `bytes` (plus whatever was left over
+    // from the previous call) allow, retaining any partial message for the next call. System
+    // Real-Time bytes (0xF8-0xFF) are dropped wherever they appear, since they're single-byte
+    // messages that can interleave mid-message without disturbing running status.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<MidiEvent> {
+        self.pending.extend(bytes.iter().copied().filter(|b| !(0xf8..=0xff).contains(b)));
+
+        let mut events = Vec::new();
+        let mut cur: &[u8] = &self.pending;
+
+        while let Some(&first) = cur.first() {
+            let is_status_byte = bits::msb_set(first);
+            let status = if is_status_byte {
+                first
+            } else if let Some(running) = self.running_status {
+                running
+            } else {
+                // stray data byte with no running status to anchor it - drop and move on
+                cur = &cur[1..];
+                continue;
+            };
+
+            let data_len = match bits::msb(status) {
+                NOTE_OFF_STATUS | NOTE_ON_STATUS | POLY_KEY_PRESSURE_STATUS
+                    | CONTROL_CHANGE_STATUS | PITCH_WHEEL_CHANGE_STATUS => 2,
+                PROGRAM_CHANGE_STATUS | CHANNEL_PRESSURE_STATUS => 1,
+                // a System Common message (0xF0-0xF7, the only status bytes that land here since
+                // Real-Time bytes are filtered out above). these aren't channel-voice messages and,
+                // per spec, cancel running status - without clearing it here the data bytes that
+                // follow (e.g. a SysEx payload) would get misread as the previous channel message
+                _ => {
+                    self.running_status = None;
+                    cur = &cur[1..];
+                    continue;
+                }
+            };
+
+            let header_len = if is_status_byte { 1 } else { 0 };
+            let needed = header_len + data_len;
+            if cur.len() < needed {
+                break; // wait for the rest of the message on the next push
+            }
+
+            let data = &cur[header_len..needed];
+            self.running_status = Some(status);
+            let channel = bits::lsb(status);
+
+            events.push(match bits::msb(status) {
+                NOTE_OFF_STATUS => MidiEvent::NoteOff { note: data[0], velocity: data[1], channel },
+                NOTE_ON_STATUS => MidiEvent::NoteOn { note: data[0], velocity: data[1], channel },
+                POLY_KEY_PRESSURE_STATUS => MidiEvent::PolyKeyPressure { channel, note: data[0], pressure: data[1] },
+                CONTROL_CHANGE_STATUS => MidiEvent::ControlChange { channel, controller: data[0], value: data[1] },
+                PROGRAM_CHANGE_STATUS => MidiEvent::ProgramChange { channel, program: data[0] },
+                CHANNEL_PRESSURE_STATUS => MidiEvent::ChannelPressure { channel, pressure: data[0] },
+                PITCH_WHEEL_CHANGE_STATUS => MidiEvent::PitchBend { channel, value: u16::from(data[0]) | (u16::from(data[1]) << 7) },
+                _ => unreachable!("data_len match above already filtered to channel-voice tags"),
+            });
+
+            cur = &cur[needed..];
+        }
+
+        let consumed = self.pending.len() - cur.len();
+        self.pending.drain(0..consumed);
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_split_across_pushes() {
+        let mut parser = StreamParser::new();
+        // note on, channel 0 - status byte and first data byte arrive first...
+        assert_eq!(parser.push(&[0x90, 60]), vec![]);
+        // ...velocity arrives in a later push
+        assert_eq!(parser.push(&[100]), vec![MidiEvent::NoteOn { note: 60, velocity: 100, channel: 0 }]);
+    }
+
+    #[test]
+    fn running_status_reused_across_pushes_without_a_status_byte() {
+        let mut parser = StreamParser::new();
+        assert_eq!(parser.push(&[0x90, 60, 100]), vec![MidiEvent::NoteOn { note: 60, velocity: 100, channel: 0 }]);
+        // no status byte this time - running status from the previous message should carry over
+        assert_eq!(parser.push(&[61, 101]), vec![MidiEvent::NoteOn { note: 61, velocity: 101, channel: 0 }]);
+    }
+
+    #[test]
+    fn system_common_byte_cancels_running_status() {
+        let mut parser = StreamParser::new();
+        assert_eq!(parser.push(&[0x90, 60, 100]), vec![MidiEvent::NoteOn { note: 60, velocity: 100, channel: 0 }]);
+        // 0xf1 is a System Common message (MTC quarter frame, 1 data byte) - it must cancel
+        // running status, so the bare data bytes that follow shouldn't be misread as NoteOn
+        parser.push(&[0xf1, 0x00]);
+        assert_eq!(parser.push(&[61, 101]), vec![]);
+    }
+
+    #[test]
+    fn real_time_bytes_are_filtered_out_mid_message() {
+        let mut parser = StreamParser::new();
+        // 0xf8 (timing clock) lands between the status byte and its data bytes - it must be
+        // dropped without disturbing the in-progress message
+        assert_eq!(parser.push(&[0x90, 0xf8, 60, 0xfe, 100]), vec![MidiEvent::NoteOn { note: 60, velocity: 100, channel: 0 }]);
+    }
+
+    #[test]
+    fn stray_data_byte_with_no_running_status_is_dropped() {
+        let mut parser = StreamParser::new();
+        assert_eq!(parser.push(&[60, 100]), vec![]);
+        // the parser should recover cleanly once a real status byte shows up
+        assert_eq!(parser.push(&[0x90, 60, 100]), vec![MidiEvent::NoteOn { note: 60, velocity: 100, channel: 0 }]);
+    }
+
+    #[test]
+    fn single_byte_channel_voice_messages_use_one_data_byte() {
+        let mut parser = StreamParser::new();
+        // program change, channel 2
+        assert_eq!(parser.push(&[0xc2, 5]), vec![MidiEvent::ProgramChange { channel: 2, program: 5 }]);
+    }
+}